@@ -1,12 +1,29 @@
 use std::{error::Error, fmt::Display};
 
-use bevy::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues};
+use bevy::mesh::{
+    Indices, Mesh, MeshVertexAttributeId, PrimitiveTopology, VertexAttributeValues,
+};
 use bytemuck::cast_slice;
 
 pub use meshopt::clusterize::Meshlets;
 
+pub use meshopt::Bounds;
+
 pub use meshopt::SimplifyOptions;
 
+mod virtual_geometry;
+pub use virtual_geometry::{build_meshlet_lod_dag, DagMeshlet, MeshletDagParams, MeshletLodDag};
+
+/// [`Meshlets`] paired with per-meshlet culling [`Bounds`].
+///
+/// The `bounds` vector runs parallel to the meshlets, carrying the bounding sphere
+/// (center + radius) and the normal cone (apex, axis, cutoff) needed for frustum and
+/// backface-cone culling of clusters.
+pub struct MeshletsWithBounds {
+    pub meshlets: Meshlets,
+    pub bounds: Vec<Bounds>,
+}
+
 pub trait MeshExt {
     /// Assert that the mesh has u32 indices, replaces if it is u16.
     fn assert_indices_u32(&mut self);
@@ -17,6 +34,43 @@ pub trait MeshExt {
     fn simplify_new_indices(&self, params: &SimplifyParams) -> Result<(Vec<u32>, f32), OptError>;
     /// [`meshopt::simplify`]
     fn simplify(&mut self, params: &SimplifyParams) -> Result<f32, OptError>;
+    /// [`meshopt::simplify_scale`]: the geometry's scaling factor, derived from the
+    /// position bounding extent. Multiply a normalized simplification error by this
+    /// to obtain a world-space distance.
+    fn simplify_scale(&self) -> Result<f32, OptError>;
+    /// Produce a discrete LOD chain in one call, one level per entry in `levels`.
+    ///
+    /// Each level simplifies the *previous* level's output index buffer rather than
+    /// the original, so error accumulates monotonically down the chain. When strict
+    /// simplification stalls far above its target ratio the level falls back to sloppy
+    /// simplification to force progress. Returns each level's index buffer together
+    /// with its accumulated absolute error, ready to drive distance-based switching.
+    fn generate_lods(
+        &self,
+        levels: &[SimplifyParams],
+    ) -> Result<Vec<(Vec<u32>, f32)>, OptError>;
+    /// [`meshopt::generate_vertex_remap_multi`] over the present vertex attributes, one
+    /// stream each for the first 16 (meshopt's stream limit); any further attributes are
+    /// not compared. Builds the `(unique_vertex_count, remap)` table that deduplicates vertices.
+    /// Unindexed `TriangleList` meshes are treated as a trivial `0..vertex_count`
+    /// index set first.
+    fn generate_remap(&self) -> Result<(usize, Vec<u32>), OptError>;
+    /// Deduplicate vertices in place: build a remap via [`generate_remap`](Self::generate_remap),
+    /// then rewrite every vertex attribute buffer and the index buffer through it. This
+    /// is a prerequisite for the cache/overdraw/fetch optimizers on redundant or
+    /// unindexed imports.
+    fn reindex(&mut self) -> Result<(), OptError>;
+    /// [`meshopt::generate_shadow_index_buffer`]: a position-only index buffer that
+    /// merges vertices identical in position, ignoring normals/UVs. Rendering depth and
+    /// shadow passes with it improves vertex-cache reuse and reduces overdraw work.
+    fn generate_shadow_indices(&self) -> Result<Vec<u32>, OptError>;
+    /// [`meshopt::generate_shadow_index_buffer_multi`]: like
+    /// [`generate_shadow_indices`](Self::generate_shadow_indices) but also keys the
+    /// vertex equivalence test on the given attributes (e.g. skinning weights).
+    fn generate_shadow_indices_multi(
+        &self,
+        attributes: &[MeshVertexAttributeId],
+    ) -> Result<Vec<u32>, OptError>;
     /// [`meshopt::optimize_vertex_fetch`]
     fn optimize_vertex_fetch(&mut self) -> Result<(), OptError>;
     /// [`meshopt::optimize_overdraw`]
@@ -30,6 +84,14 @@ pub trait MeshExt {
         max_triangles: usize,
         cone_weight: f32,
     ) -> Result<Meshlets, OptError>;
+    /// Build meshlets together with per-meshlet culling [`Bounds`] via
+    /// [`meshopt::compute_meshlet_bounds`] (TriangleList topology required).
+    fn meshlets_with_bounds(
+        &self,
+        max_vertices: usize,
+        max_triangles: usize,
+        cone_weight: f32,
+    ) -> Result<MeshletsWithBounds, OptError>;
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -68,6 +130,20 @@ pub struct SimplifyParams<'a> {
     pub sloppy: bool,
     /// Lock specific vertices in place during simplification.
     pub vertex_locks: Option<&'a [bool]>,
+    /// Interpret [`max_error`](Self::max_error) and the returned error as world-space
+    /// distance rather than meshopt's normalized units. The error bound is divided by
+    /// [`simplify_scale`](MeshExt::simplify_scale) before simplification and the result
+    /// is multiplied back, so callers can ask to "simplify until deviation exceeds 2cm"
+    /// consistently across meshes of different world sizes.
+    pub absolute_error: bool,
+    /// Vertex attributes to preserve alongside position, each with a weight.
+    ///
+    /// Position error alone collapses shading and texture detail in LODs, so each
+    /// listed attribute (normals, UVs, vertex colors, …) is read from the mesh and
+    /// fed to `meshopt_simplifyWithAttributes`. Higher weights make the simplifier
+    /// guard the corresponding discontinuities (UV seams, hard normals) more
+    /// aggressively. Ignored in sloppy mode, which only minimizes position error.
+    pub attributes: &'a [(MeshVertexAttributeId, f32)],
 }
 
 impl Default for SimplifyParams<'_> {
@@ -78,6 +154,8 @@ impl Default for SimplifyParams<'_> {
             options: SimplifyOptions::None,
             sloppy: false,
             vertex_locks: None,
+            absolute_error: false,
+            attributes: &[],
         }
     }
 }
@@ -87,6 +165,8 @@ pub enum OptError {
     MissingIndices,
     UnsupportedIndexFormat,
     MissingPositions,
+    MissingAttribute(MeshVertexAttributeId),
+    UnsupportedAttributeFormat(MeshVertexAttributeId),
     UnsupportedPrimitiveTopology(PrimitiveTopology),
     InvalidIndexCount(usize),
     Meshopt(String),
@@ -98,6 +178,10 @@ impl Display for OptError {
             OptError::MissingIndices => write!(f, "Missing indices"),
             OptError::UnsupportedIndexFormat => write!(f, "Unsupported index format"),
             OptError::MissingPositions => write!(f, "Missing positions"),
+            OptError::MissingAttribute(id) => write!(f, "Missing attribute: {:?}", id),
+            OptError::UnsupportedAttributeFormat(id) => {
+                write!(f, "Unsupported attribute format for {:?}", id)
+            }
             OptError::UnsupportedPrimitiveTopology(topology) => write!(
                 f,
                 "Unsupported topology: {:?}, bevy_meshopt currently only works with `TriangleList` topology,",
@@ -127,7 +211,7 @@ fn assert_u32_indices(indices: Option<&mut Indices>) {
     }
 }
 
-fn mesh_indices(mesh: &Mesh) -> Result<&Vec<u32>, OptError> {
+pub(crate) fn mesh_indices(mesh: &Mesh) -> Result<&Vec<u32>, OptError> {
     let indices = match mesh.indices() {
         Some(Indices::U32(indices)) => indices,
         Some(_) => return Err(OptError::UnsupportedIndexFormat),
@@ -172,7 +256,7 @@ fn take_mesh_indices_mut(mesh: &mut Mesh) -> Result<Vec<u32>, OptError> {
     return Ok(indices);
 }
 
-fn mesh_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
+pub(crate) fn mesh_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
     let PrimitiveTopology::TriangleList = mesh.primitive_topology() else {
         return Err(OptError::UnsupportedPrimitiveTopology(
             mesh.primitive_topology(),
@@ -189,6 +273,186 @@ fn mesh_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
     Ok(positions)
 }
 
+/// Read a vertex attribute as a flat `f32` slice plus its component count per vertex.
+///
+/// Only the float formats meshopt can consume (`Float32x2`/`Float32x3`/`Float32x4`)
+/// are supported; anything else is reported as [`OptError::UnsupportedAttributeFormat`].
+fn attribute_floats(
+    mesh: &Mesh,
+    id: MeshVertexAttributeId,
+) -> Result<(usize, &[f32]), OptError> {
+    match mesh.attribute(id) {
+        Some(VertexAttributeValues::Float32x2(values)) => Ok((2, cast_slice(values.as_slice()))),
+        Some(VertexAttributeValues::Float32x3(values)) => Ok((3, cast_slice(values.as_slice()))),
+        Some(VertexAttributeValues::Float32x4(values)) => Ok((4, cast_slice(values.as_slice()))),
+        Some(_) => Err(OptError::UnsupportedAttributeFormat(id)),
+        None => Err(OptError::MissingAttribute(id)),
+    }
+}
+
+/// Build the interleaved attribute buffer and per-component weights meshopt expects.
+///
+/// The attribute buffer holds `attribute_count` floats per vertex in the same vertex
+/// order as positions, and `attribute_weights` carries one weight per float component.
+fn build_vertex_attributes(
+    mesh: &Mesh,
+    vertex_count: usize,
+    attributes: &[(MeshVertexAttributeId, f32)],
+) -> Result<(Vec<f32>, Vec<f32>, usize), OptError> {
+    let mut sources = Vec::with_capacity(attributes.len());
+    let mut weights = Vec::new();
+    let mut attribute_count = 0;
+    for &(id, weight) in attributes {
+        let (components, values) = attribute_floats(mesh, id)?;
+        attribute_count += components;
+        weights.extend(std::iter::repeat(weight).take(components));
+        sources.push((components, values));
+    }
+
+    let mut vertex_attributes = Vec::with_capacity(vertex_count * attribute_count);
+    for vertex in 0..vertex_count {
+        for &(components, values) in &sources {
+            let start = vertex * components;
+            vertex_attributes.extend_from_slice(&values[start..start + components]);
+        }
+    }
+
+    Ok((vertex_attributes, weights, attribute_count))
+}
+
+/// Simplify an explicit index buffer against `mesh`'s position/attribute data.
+///
+/// Shared by [`MeshExt::simplify_new_indices`] and [`MeshExt::generate_lods`]; the
+/// latter feeds each level the previous level's output rather than the mesh indices.
+pub(crate) fn simplify_indices(
+    mesh: &Mesh,
+    indices: &[u32],
+    params: &SimplifyParams,
+) -> Result<(Vec<u32>, f32), OptError> {
+    let positions = mesh_positions(mesh)?;
+
+    let target_index_count = params.target_index_count.count(indices.len());
+
+    let scale = if params.absolute_error {
+        meshopt::simplify_scale_decoder(positions.as_slice())
+    } else {
+        1.0
+    };
+    // meshopt works in normalized units, so convert a world-space bound down first.
+    let max_error = if params.absolute_error && scale != 0.0 {
+        params.max_error / scale
+    } else {
+        params.max_error
+    };
+
+    let mut result_error = 0.0;
+    let new_indices = if params.sloppy {
+        if let Some(locks) = params.vertex_locks {
+            meshopt::simplify_sloppy_with_locks_decoder(
+                indices,
+                &positions,
+                locks,
+                target_index_count,
+                max_error,
+                Some(&mut result_error),
+            )
+        } else {
+            meshopt::simplify_sloppy_decoder(
+                indices,
+                positions.as_slice(),
+                target_index_count,
+                max_error,
+                Some(&mut result_error),
+            )
+        }
+    } else if !params.attributes.is_empty() {
+        let (vertex_attributes, attribute_weights, attribute_count) =
+            build_vertex_attributes(mesh, positions.len(), params.attributes)?;
+        let locks = params.vertex_locks.unwrap_or(&[]);
+        meshopt::simplify_with_attributes_decoder(
+            indices,
+            positions.as_slice(),
+            &vertex_attributes,
+            &attribute_weights,
+            attribute_count,
+            locks,
+            target_index_count,
+            max_error,
+            params.options,
+            Some(&mut result_error),
+        )
+    } else {
+        if let Some(locks) = params.vertex_locks {
+            meshopt::simplify_with_locks_decoder(
+                indices,
+                positions.as_slice(),
+                locks,
+                target_index_count,
+                max_error,
+                params.options,
+                Some(&mut result_error),
+            )
+        } else {
+            meshopt::simplify_decoder(
+                indices,
+                positions.as_slice(),
+                target_index_count,
+                max_error,
+                params.options,
+                Some(&mut result_error),
+            )
+        }
+    };
+
+    if params.absolute_error {
+        result_error *= scale;
+    }
+
+    Ok((new_indices, result_error))
+}
+
+/// The input index buffer for remap generation, synthesizing a trivial one when absent.
+fn remap_input_indices(mesh: &Mesh) -> Result<Vec<u32>, OptError> {
+    match mesh.indices() {
+        Some(Indices::U32(indices)) => Ok(indices.clone()),
+        Some(_) => Err(OptError::UnsupportedIndexFormat),
+        None => {
+            let PrimitiveTopology::TriangleList = mesh.primitive_topology() else {
+                return Err(OptError::UnsupportedPrimitiveTopology(
+                    mesh.primitive_topology(),
+                ));
+            };
+            Ok((0..mesh.count_vertices() as u32).collect())
+        }
+    }
+}
+
+/// Apply a meshopt remap table to a single attribute buffer, preserving its format.
+fn remap_attribute_values(
+    values: &VertexAttributeValues,
+    unique_count: usize,
+    remap: &[u32],
+) -> VertexAttributeValues {
+    macro_rules! remap {
+        ($($variant:ident),+ $(,)?) => {
+            match values {
+                $(
+                    VertexAttributeValues::$variant(data) => VertexAttributeValues::$variant(
+                        meshopt::remap_vertex_buffer(data.as_slice(), unique_count, remap),
+                    ),
+                )+
+            }
+        };
+    }
+
+    remap!(
+        Float32, Sint32, Uint32, Float32x2, Sint32x2, Uint32x2, Float32x3, Sint32x3, Uint32x3,
+        Float32x4, Sint32x4, Uint32x4, Sint16x2, Snorm16x2, Uint16x2, Unorm16x2, Sint16x4,
+        Snorm16x4, Uint16x4, Unorm16x4, Sint8x2, Snorm8x2, Uint8x2, Unorm8x2, Sint8x4, Snorm8x4,
+        Uint8x4, Unorm8x4,
+    )
+}
+
 impl MeshExt for Mesh {
     fn assert_indices_u32(&mut self) {
         assert_u32_indices(self.indices_mut());
@@ -210,54 +474,137 @@ impl MeshExt for Mesh {
 
     fn simplify_new_indices(&self, params: &SimplifyParams) -> Result<(Vec<u32>, f32), OptError> {
         let indices = mesh_indices(self)?;
+        simplify_indices(self, indices, params)
+    }
+
+    fn simplify_scale(&self) -> Result<f32, OptError> {
         let positions = mesh_positions(self)?;
+        Ok(meshopt::simplify_scale_decoder(positions.as_slice()))
+    }
 
-        let target_index_count = params.target_index_count.count(indices.len());
-
-        let mut result_error = 0.0;
-        let new_indices = if params.sloppy {
-            if let Some(locks) = params.vertex_locks {
-                meshopt::simplify_sloppy_with_locks_decoder(
-                    indices,
-                    &positions,
-                    locks,
-                    target_index_count,
-                    params.max_error,
-                    Some(&mut result_error),
-                )
-            } else {
-                meshopt::simplify_sloppy_decoder(
-                    indices,
-                    positions.as_slice(),
-                    target_index_count,
-                    params.max_error,
-                    Some(&mut result_error),
-                )
+    fn generate_lods(
+        &self,
+        levels: &[SimplifyParams],
+    ) -> Result<Vec<(Vec<u32>, f32)>, OptError> {
+        let mut current = mesh_indices(self)?.clone();
+        let scale = self.simplify_scale()?;
+
+        let mut lods = Vec::with_capacity(levels.len());
+        let mut accumulated_error = 0.0;
+        for params in levels {
+            let target = params.target_index_count.count(current.len());
+
+            let (mut indices, mut error) = simplify_indices(self, &current, params)?;
+            // Strict simplification can stall far above the target ratio; fall back to
+            // sloppy simplification (which ignores topology) to force progress.
+            if !params.sloppy && indices.len() > target * 2 {
+                let sloppy = SimplifyParams {
+                    sloppy: true,
+                    ..*params
+                };
+                let (sloppy_indices, sloppy_error) = simplify_indices(self, &current, &sloppy)?;
+                if sloppy_indices.len() < indices.len() {
+                    indices = sloppy_indices;
+                    error = sloppy_error;
+                }
             }
-        } else {
-            if let Some(locks) = params.vertex_locks {
-                meshopt::simplify_with_locks_decoder(
-                    indices,
-                    positions.as_slice(),
-                    locks,
-                    target_index_count,
-                    params.max_error,
-                    params.options,
-                    Some(&mut result_error),
-                )
-            } else {
-                meshopt::simplify_decoder(
-                    indices,
-                    positions.as_slice(),
-                    target_index_count,
-                    params.max_error,
-                    params.options,
-                    Some(&mut result_error),
-                )
+
+            // Levels with a normalized bound still report world-space error.
+            if !params.absolute_error {
+                error *= scale;
             }
-        };
 
-        Ok((new_indices, result_error))
+            accumulated_error += error;
+            current = indices;
+            lods.push((current.clone(), accumulated_error));
+        }
+
+        Ok(lods)
+    }
+
+    fn generate_remap(&self) -> Result<(usize, Vec<u32>), OptError> {
+        let vertex_count = self.count_vertices();
+        let indices = remap_input_indices(self)?;
+
+        // One stream per attribute, capped at meshopt's limit of 16.
+        let sources: Vec<&[u8]> = self
+            .attributes()
+            .take(16)
+            .map(|(_, values)| values.get_bytes())
+            .collect();
+        let streams: Vec<meshopt::VertexStream> = sources
+            .iter()
+            .map(|bytes| {
+                let stride = bytes.len() / vertex_count.max(1);
+                meshopt::VertexStream::new_with_stride(bytes.as_ptr(), stride)
+            })
+            .collect();
+
+        let (unique_count, remap) =
+            meshopt::generate_vertex_remap_multi(vertex_count, Some(&indices), &streams);
+        Ok((unique_count, remap))
+    }
+
+    fn reindex(&mut self) -> Result<(), OptError> {
+        let indices = remap_input_indices(self)?;
+        let (unique_count, remap) = self.generate_remap()?;
+
+        let ids: Vec<MeshVertexAttributeId> =
+            self.attributes().map(|(attribute, _)| attribute.id).collect();
+        for id in ids {
+            if let Some(values) = self.attribute_mut(id) {
+                *values = remap_attribute_values(values, unique_count, &remap);
+            }
+        }
+
+        let new_indices = meshopt::remap_index_buffer(Some(&indices), indices.len(), &remap);
+        self.insert_indices(Indices::U32(new_indices));
+        Ok(())
+    }
+
+    fn generate_shadow_indices(&self) -> Result<Vec<u32>, OptError> {
+        let indices = mesh_indices(self)?;
+        let positions = mesh_positions(self)?;
+
+        let adapter = meshopt::VertexDataAdapter::new(
+            cast_slice(positions.as_slice()),
+            std::mem::size_of::<[f32; 3]>(),
+            0,
+        )
+        .map_err(|e| OptError::Meshopt(e.to_string()))?;
+
+        Ok(meshopt::generate_shadow_index_buffer(indices, &adapter))
+    }
+
+    fn generate_shadow_indices_multi(
+        &self,
+        attributes: &[MeshVertexAttributeId],
+    ) -> Result<Vec<u32>, OptError> {
+        let indices = mesh_indices(self)?;
+        let positions = mesh_positions(self)?;
+
+        // Position is always part of the equivalence test; extra streams refine it.
+        let position_floats: &[f32] = cast_slice(positions.as_slice());
+        let mut sources = vec![(3usize, position_floats)];
+        for &id in attributes {
+            sources.push(attribute_floats(self, id)?);
+        }
+
+        let streams: Vec<meshopt::VertexStream> = sources
+            .iter()
+            .map(|&(components, values)| {
+                meshopt::VertexStream::new_with_stride(
+                    values.as_ptr(),
+                    components * std::mem::size_of::<f32>(),
+                )
+            })
+            .collect();
+
+        Ok(meshopt::generate_shadow_index_buffer_multi(
+            indices,
+            positions.len(),
+            &streams,
+        ))
     }
 
     fn optimize_vertex_fetch(&mut self) -> Result<(), OptError> {
@@ -312,4 +659,35 @@ impl MeshExt for Mesh {
             cone_weight,
         ))
     }
+
+    fn meshlets_with_bounds(
+        &self,
+        max_vertices: usize,
+        max_triangles: usize,
+        cone_weight: f32,
+    ) -> Result<MeshletsWithBounds, OptError> {
+        let indices = mesh_indices(self)?;
+        let positions = mesh_positions(self)?;
+
+        let adapter = meshopt::VertexDataAdapter::new(
+            cast_slice(positions.as_slice()),
+            std::mem::size_of::<[f32; 3]>(),
+            0,
+        )
+        .map_err(|e| OptError::Meshopt(e.to_string()))?;
+
+        let meshlets = meshopt::clusterize::build_meshlets(
+            indices,
+            &adapter,
+            max_vertices,
+            max_triangles,
+            cone_weight,
+        );
+
+        let bounds = (0..meshlets.len())
+            .map(|i| meshopt::compute_meshlet_bounds(meshlets.get(i), &adapter))
+            .collect();
+
+        Ok(MeshletsWithBounds { meshlets, bounds })
+    }
 }
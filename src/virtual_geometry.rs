@@ -0,0 +1,302 @@
+//! Nanite-style hierarchical meshlet LOD DAG.
+//!
+//! Builds a virtual-geometry LOD hierarchy on top of [`MeshExt::meshlets`](crate::MeshExt::meshlets):
+//! leaf meshlets are grouped by spatial adjacency, each group is simplified and
+//! re-clustered into the next coarser LOD level, and the process repeats until a
+//! single meshlet remains or a level stalls (no group loses triangles, or the meshlet
+//! count does not drop), in which case the DAG ends with several root meshlets. Every meshlet records a bounding sphere together with its
+//! own group error and its parent group's error, stored monotonically so that a
+//! runtime error-bounded cut through the DAG is always crack-free.
+
+use std::collections::HashMap;
+
+use metis::Graph;
+
+use crate::{mesh_indices, mesh_positions, Bounds, OptError, SimplifyOptions, SimplifyParams};
+use bevy::mesh::Mesh;
+use bytemuck::cast_slice;
+
+/// Tuning for [`build_meshlet_lod_dag`].
+#[derive(Debug, Copy, Clone)]
+pub struct MeshletDagParams {
+    /// Maximum vertices per meshlet, forwarded to `build_meshlets`.
+    pub max_vertices: usize,
+    /// Maximum triangles per meshlet, forwarded to `build_meshlets`.
+    pub max_triangles: usize,
+    /// Cone weight balancing spatial locality against normal coherence.
+    pub cone_weight: f32,
+    /// Target number of meshlets per group when partitioning (~4–8).
+    pub group_size: usize,
+}
+
+impl Default for MeshletDagParams {
+    fn default() -> Self {
+        MeshletDagParams {
+            max_vertices: 64,
+            max_triangles: 124,
+            cone_weight: 0.0,
+            group_size: 8,
+        }
+    }
+}
+
+/// A single meshlet within a [`MeshletLodDag`].
+#[derive(Debug, Clone)]
+pub struct DagMeshlet {
+    /// Triangle list indexing the mesh's original vertex buffer.
+    pub indices: Vec<u32>,
+    /// Bounding sphere and normal cone for culling.
+    pub bounds: Bounds,
+    /// LOD level this meshlet belongs to; 0 is the finest.
+    pub lod_level: u32,
+    /// Error of the group this meshlet was produced from (monotonic up the DAG).
+    pub self_error: f32,
+    /// Error of the parent group this meshlet is simplified into; [`f32::MAX`] at the root.
+    pub parent_error: f32,
+}
+
+/// The output LOD DAG: every meshlet across every level, with parent error bounds.
+#[derive(Debug, Clone)]
+pub struct MeshletLodDag {
+    pub meshlets: Vec<DagMeshlet>,
+}
+
+/// Build a hierarchical meshlet LOD DAG for `mesh` (TriangleList topology required).
+pub fn build_meshlet_lod_dag(
+    mesh: &Mesh,
+    params: &MeshletDagParams,
+) -> Result<MeshletLodDag, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+
+    let adapter = meshopt::VertexDataAdapter::new(
+        cast_slice(positions.as_slice()),
+        std::mem::size_of::<[f32; 3]>(),
+        0,
+    )
+    .map_err(|e| OptError::Meshopt(e.to_string()))?;
+
+    let mut meshlets: Vec<DagMeshlet> = Vec::new();
+
+    // Level 0: the leaf meshlets have no simplification error yet.
+    let mut current: Vec<usize> =
+        cluster_into(mesh, &adapter, indices, params, 0, 0.0, &mut meshlets);
+
+    let mut level = 1;
+    while current.len() > 1 {
+        let nparts = current.len().div_ceil(params.group_size).max(1);
+        let groups = partition_meshlets(&current, &meshlets, nparts);
+
+        let mut next = Vec::new();
+        let mut progressed = false;
+        for group in &groups {
+            if group.is_empty() {
+                continue;
+            }
+
+            let merged = merge_group_indices(group, &meshlets);
+            let locks = group_boundary_locks(&merged, positions.len());
+
+            let target = SimplifyParams {
+                target_index_count: crate::TargetIndices::Multiplier(0.5),
+                options: SimplifyOptions::LockBorder,
+                vertex_locks: Some(&locks),
+                ..Default::default()
+            };
+            let (simplified, error) =
+                crate::simplify_indices(mesh, &merged, &target)?;
+
+            // Monotonic group error: never smaller than any child's error.
+            let child_error = group
+                .iter()
+                .map(|&id| meshlets[id].self_error)
+                .fold(0.0_f32, f32::max);
+            let group_error = child_error.max(child_error + error);
+
+            for &id in group {
+                meshlets[id].parent_error = group_error;
+            }
+
+            if simplified.len() < merged.len() {
+                progressed = true;
+            }
+
+            next.extend(cluster_into(
+                mesh,
+                &adapter,
+                &simplified,
+                params,
+                level,
+                group_error,
+                &mut meshlets,
+            ));
+        }
+
+        // Bail out if simplification stalled, to avoid an unbounded hierarchy.
+        if !progressed || next.len() >= current.len() {
+            break;
+        }
+
+        current = next;
+        level += 1;
+    }
+
+    Ok(MeshletLodDag { meshlets })
+}
+
+/// Cluster `indices` into meshlets, push them onto `meshlets`, and return their ids.
+fn cluster_into(
+    _mesh: &Mesh,
+    adapter: &meshopt::VertexDataAdapter,
+    indices: &[u32],
+    params: &MeshletDagParams,
+    level: u32,
+    self_error: f32,
+    meshlets: &mut Vec<DagMeshlet>,
+) -> Vec<usize> {
+    if indices.len() < 3 {
+        return Vec::new();
+    }
+
+    let built = meshopt::clusterize::build_meshlets(
+        indices,
+        adapter,
+        params.max_vertices,
+        params.max_triangles,
+        params.cone_weight,
+    );
+
+    let mut ids = Vec::with_capacity(built.len());
+    for i in 0..built.len() {
+        let meshlet = built.get(i);
+        let bounds = meshopt::compute_meshlet_bounds(meshlet, adapter);
+        ids.push(meshlets.len());
+        meshlets.push(DagMeshlet {
+            indices: meshlet_indices(&meshlet),
+            bounds,
+            lod_level: level,
+            self_error,
+            parent_error: f32::MAX,
+        });
+    }
+    ids
+}
+
+/// Flatten a meshlet's local triangles into a global index buffer.
+fn meshlet_indices(meshlet: &meshopt::Meshlet) -> Vec<u32> {
+    meshlet
+        .triangles
+        .iter()
+        .map(|&local| meshlet.vertices[local as usize])
+        .collect()
+}
+
+/// Concatenate the index buffers of every meshlet in a group.
+fn merge_group_indices(group: &[usize], meshlets: &[DagMeshlet]) -> Vec<u32> {
+    let mut merged = Vec::new();
+    for &id in group {
+        merged.extend_from_slice(&meshlets[id].indices);
+    }
+    merged
+}
+
+/// Lock a vertex only if every edge incident to it lies on the group's outer boundary.
+///
+/// A boundary edge is shared by exactly one triangle in the merged group; internal
+/// edges shared between triangles stay free so the simplifier can collapse them.
+fn group_boundary_locks(indices: &[u32], vertex_count: usize) -> Vec<bool> {
+    let mut edge_counts: HashMap<(u32, u32), u32> = HashMap::new();
+    for tri in indices.chunks_exact(3) {
+        for &(a, b) in &[(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+            let key = if a < b { (a, b) } else { (b, a) };
+            *edge_counts.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    // A vertex is locked unless it touches at least one interior edge.
+    let mut interior = vec![false; vertex_count];
+    let mut present = vec![false; vertex_count];
+    for tri in indices.chunks_exact(3) {
+        for &(a, b) in &[(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+            present[a as usize] = true;
+            present[b as usize] = true;
+            let key = if a < b { (a, b) } else { (b, a) };
+            if edge_counts[&key] > 1 {
+                interior[a as usize] = true;
+                interior[b as usize] = true;
+            }
+        }
+    }
+
+    (0..vertex_count)
+        .map(|v| present[v] && !interior[v])
+        .collect()
+}
+
+/// Partition meshlets into `nparts` groups, weighting edges by shared triangle edges.
+fn partition_meshlets(
+    ids: &[usize],
+    meshlets: &[DagMeshlet],
+    nparts: usize,
+) -> Vec<Vec<usize>> {
+    if nparts <= 1 {
+        return vec![ids.to_vec()];
+    }
+
+    // Map every triangle edge to the meshlets that contain it.
+    let mut edge_owners: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (local, &id) in ids.iter().enumerate() {
+        for tri in meshlets[id].indices.chunks_exact(3) {
+            for &(a, b) in &[(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                let key = if a < b { (a, b) } else { (b, a) };
+                let owners = edge_owners.entry(key).or_default();
+                if owners.last() != Some(&local) {
+                    owners.push(local);
+                }
+            }
+        }
+    }
+
+    // Accumulate adjacency weights from meshlets that share an edge.
+    let n = ids.len();
+    let mut adjacency: Vec<HashMap<usize, i32>> = vec![HashMap::new(); n];
+    for owners in edge_owners.values() {
+        for i in 0..owners.len() {
+            for j in (i + 1)..owners.len() {
+                *adjacency[owners[i]].entry(owners[j]).or_insert(0) += 1;
+                *adjacency[owners[j]].entry(owners[i]).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Build the CSR form METIS expects.
+    let mut xadj = Vec::with_capacity(n + 1);
+    let mut adjncy = Vec::new();
+    let mut adjwgt = Vec::new();
+    xadj.push(0i32);
+    for neighbors in &adjacency {
+        for (&neighbor, &weight) in neighbors {
+            adjncy.push(neighbor as i32);
+            adjwgt.push(weight);
+        }
+        xadj.push(adjncy.len() as i32);
+    }
+
+    // A graph with no edges can't be partitioned; fall back to a single group.
+    if adjncy.is_empty() {
+        return vec![ids.to_vec()];
+    }
+
+    let mut part = vec![0i32; n];
+    let partitioned = Graph::new(1, nparts as i32, &mut xadj, &mut adjncy)
+        .and_then(|g| g.set_adjwgt(&mut adjwgt).part_kway(&mut part));
+    if partitioned.is_err() {
+        return vec![ids.to_vec()];
+    }
+
+    let mut groups = vec![Vec::new(); nparts];
+    for (local, &p) in part.iter().enumerate() {
+        groups[p as usize].push(ids[local]);
+    }
+    groups
+}